@@ -5,7 +5,11 @@ use bevy::{
     window, window::PresentMode, window::WindowMode,
 };
 use heron::{CollisionEvent, CollisionShape, PhysicsPlugin, RigidBody};
-use std::{collections::VecDeque, f32::consts::PI};
+use noise::{NoiseFn, Perlin};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    f32::consts::PI,
+};
 const BACKGROUND_COLOR: Color = Color::rgb(43. / 255., 32. / 255., 35. / 255.);
 const TIME_STEP: f32 = 1.0 / 60.0;
 const BIKE_SPEED: f32 = 400.0;
@@ -29,6 +33,157 @@ enum AppState {
 #[derive(Component, Deref, DerefMut)]
 struct AnimationTimer(Timer);
 
+// Wall-clock survival time and peak trail length for the current run, reset
+// to zero each time `restart_game` spawns a fresh player.
+#[derive(Default)]
+struct Score {
+    survival_time: f32,
+    trail_length: usize,
+}
+
+#[derive(Component)]
+struct ScoreHud;
+
+#[derive(Component)]
+struct DeadOverlay;
+
+// The loaded bike spritesheet, kept around so `restart_game` can respawn
+// bikes without reloading assets.
+struct BikeAtlas(Handle<TextureAtlas>);
+
+// Half-extent of the arena boundary, in grid cells (see `grid_cell`).
+struct ArenaSize {
+    half_width_cells: i32,
+    half_height_cells: i32,
+}
+
+impl Default for ArenaSize {
+    fn default() -> Self {
+        Self {
+            half_width_cells: 75,
+            half_height_cells: 75,
+        }
+    }
+}
+
+// Seeds the obstacle-field noise so a given seed always generates the same map.
+struct ArenaSeed(u32);
+
+impl Default for ArenaSeed {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+// Runtime-tunable bike movement; `BIKE_DELTA`/`TRAIL_BLOCK_HALF` stay fixed
+// constants sizing the world grid.
+struct MovementSettings {
+    base_speed: f32,
+    // How fast speed closes on its target each second.
+    accel: f32,
+}
+
+impl MovementSettings {
+    fn tick_delta(&self, speed: f32) -> f32 {
+        speed * TIME_STEP
+    }
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            base_speed: BIKE_SPEED,
+            accel: BIKE_SPEED * 4.0,
+        }
+    }
+}
+
+// Abstract actions `gather_input` maps physical bindings onto.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum InputAction {
+    TurnLeft,
+    TurnRight,
+    TurnUp,
+    TurnDown,
+    Confirm,
+}
+
+// One local player's physical bindings: a keyboard layout plus whichever
+// gamepad `assign_gamepads` has handed it, if any.
+struct PlayerBindings {
+    keyboard: HashMap<InputAction, KeyCode>,
+    gamepad: Option<Gamepad>,
+}
+
+// Per-player binding sets; player 0 drives the arrow keys, player 1 drives WASD.
+struct InputBindings(Vec<PlayerBindings>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self(vec![
+            PlayerBindings {
+                keyboard: HashMap::from([
+                    (InputAction::TurnLeft, KeyCode::Left),
+                    (InputAction::TurnRight, KeyCode::Right),
+                    (InputAction::TurnUp, KeyCode::Up),
+                    (InputAction::TurnDown, KeyCode::Down),
+                    (InputAction::Confirm, KeyCode::Return),
+                ]),
+                gamepad: None,
+            },
+            PlayerBindings {
+                keyboard: HashMap::from([
+                    (InputAction::TurnLeft, KeyCode::A),
+                    (InputAction::TurnRight, KeyCode::D),
+                    (InputAction::TurnUp, KeyCode::W),
+                    (InputAction::TurnDown, KeyCode::S),
+                    (InputAction::Confirm, KeyCode::Space),
+                ]),
+                gamepad: None,
+            },
+        ])
+    }
+}
+
+// Resolved per-player input for this frame, read by `player_movement` and
+// `handle_dead_input` instead of raw keyboard/gamepad state.
+#[derive(Default, Clone, Copy)]
+struct PlayerInputState {
+    turn_left: bool,
+    turn_right: bool,
+    turn_up: bool,
+    turn_down: bool,
+    confirm_just_pressed: bool,
+}
+
+#[derive(Default)]
+struct InputState(Vec<PlayerInputState>);
+
+// Tuning for `camera_system`'s smoothed, speed-aware follow.
+struct CameraSettings {
+    // How quickly the camera closes the gap to its target each second; higher
+    // is snappier.
+    stiffness: f32,
+    // How far ahead of the bike, along its current direction, the camera aims.
+    look_ahead_distance: f32,
+    zoom_min: f32,
+    zoom_max: f32,
+    // Speed at which zoom reaches `zoom_max`; scales linearly below that.
+    zoom_speed_cap: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            stiffness: 6.0,
+            look_ahead_distance: 120.0,
+            zoom_min: 1.0,
+            zoom_max: 1.6,
+            zoom_speed_cap: BIKE_SPEED * 2.0,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 enum Direction {
     Left,
@@ -43,25 +198,117 @@ struct Bike {
     atlas_handle: Handle<TextureAtlas>,
 }
 
+// A bike's current movement speed; `current` eases toward `target` each tick.
+#[derive(Component)]
+struct Speed {
+    current: f32,
+    target: f32,
+    boost_remaining: f32,
+}
+
+impl Speed {
+    fn new(base_speed: f32) -> Self {
+        Self {
+            current: base_speed,
+            target: base_speed,
+            boost_remaining: 0.0,
+        }
+    }
+}
+
 struct TrailBlock {
     entity: Entity,
     pos: Vec3,
+    // The grid cells `block_cells` claimed for this block, running from the
+    // bike's position toward the block's head; the cell the block itself is
+    // drawn in is not among them. Tracked per block so popping the tail
+    // removes exactly the cells it claimed.
+    cells: Vec<(i32, i32)>,
 }
 
 #[derive(Component)]
 struct Trail {
     tail: VecDeque<TrailBlock>,
     capacity: usize,
+    // Grid index of occupied cells, kept in lockstep with `tail` so collision
+    // checks are O(1) instead of relying on the physics engine's discrete
+    // sensor overlap, which can tunnel through thin blocks at high speed.
+    cells: HashMap<(i32, i32), Entity>,
+}
+
+// Static obstacles (arena walls, etc.) indexed on the same grid as `Trail::cells`.
+#[derive(Default)]
+struct WallGrid(HashSet<(i32, i32)>);
+
+// A collectible pickup that temporarily raises a bike's target speed on
+// contact; see `collect_boost`.
+#[derive(Component)]
+struct Boost {
+    multiplier: f32,
+    duration: f32,
 }
 
 #[derive(Component)]
 struct Player;
 
+// Everything is grid-aligned to `BIKE_DELTA`, so a position maps to a single
+// occupancy cell by flooring its grid-relative coordinates.
+fn grid_cell(pos: Vec3) -> (i32, i32) {
+    (
+        (pos.x / BIKE_DELTA).floor() as i32,
+        (pos.y / BIKE_DELTA).floor() as i32,
+    )
+}
+
+// `start`'s grid cell plus the cells `swept_cells` walks toward `end`'s cell.
+// `trek` passes the bike's position and the new block's head, which sits
+// `delta + BIKE_WIDTH_CENTER` behind the bike and offset sideways, so a block
+// claims several cells even at base speed. When both coordinates differ only
+// the x axis is walked, along `start`'s row.
+fn block_cells(start: Vec3, end: Vec3) -> Vec<(i32, i32)> {
+    let start_cell = grid_cell(start);
+    let end_cell = grid_cell(end);
+    let mut cells = vec![start_cell];
+    cells.extend(swept_cells(start_cell, end_cell));
+    cells
+}
+
+// Walk the integer cells crossed moving from `old` to `new`, excluding `old`
+// itself. Motion is axis-aligned, so only one coordinate ever changes; this
+// still handles a multi-cell jump (e.g. a speed boost) rather than assuming
+// the usual single-cell step.
+fn swept_cells(old: (i32, i32), new: (i32, i32)) -> Vec<(i32, i32)> {
+    let mut cells = Vec::new();
+    if old.0 != new.0 {
+        let step = if new.0 > old.0 { 1 } else { -1 };
+        let mut x = old.0 + step;
+        loop {
+            cells.push((x, old.1));
+            if x == new.0 {
+                break;
+            }
+            x += step;
+        }
+    } else if old.1 != new.1 {
+        let step = if new.1 > old.1 { 1 } else { -1 };
+        let mut y = old.1 + step;
+        loop {
+            cells.push((old.0, y));
+            if y == new.1 {
+                break;
+            }
+            y += step;
+        }
+    }
+    cells
+}
+
 impl Trail {
     fn new() -> Self {
         Self {
             capacity: 1000,
             tail: VecDeque::with_capacity(100_000),
+            cells: HashMap::new(),
         }
     }
 
@@ -72,62 +319,74 @@ impl Trail {
      *  and add a new block to the front instead of moving every block.
      * Trailblocks are added to the back of the bike
      */
+    // `delta` is the bike's current per-tick step, not the flat `BIKE_DELTA`
+    // constant, so a boosted bike's trail blocks stay sized and spaced to its
+    // actual speed.
     fn trek(
         &mut self,
         bike: &mut Bike,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<ColorMaterial>>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
         bike_pos: Vec3,
         commands: &mut Commands,
+        delta: f32,
     ) {
         if self.tail.len() == self.capacity {
             // pop the back of the tail off and despawn it.
-            commands
-                .entity(self.tail.pop_back().unwrap().entity)
-                .despawn();
+            let popped = self.tail.pop_back().unwrap();
+            for cell in &popped.cells {
+                self.cells.remove(cell);
+            }
+            commands.entity(popped.entity).despawn();
         }
 
         let new_head_pos = match bike.direction {
             Direction::Down => Vec3::new(
                 bike_pos.x + BIKE_HEIGHT_CENTER,
-                bike_pos.y + BIKE_DELTA + BIKE_WIDTH_CENTER,
+                bike_pos.y + delta + BIKE_WIDTH_CENTER,
                 0.,
             ),
             Direction::Up => Vec3::new(
                 bike_pos.x + BIKE_HEIGHT_CENTER,
-                bike_pos.y - BIKE_DELTA - BIKE_WIDTH_CENTER,
+                bike_pos.y - delta - BIKE_WIDTH_CENTER,
                 0.,
             ),
             Direction::Left => Vec3::new(
-                bike_pos.x + BIKE_DELTA + BIKE_WIDTH_CENTER,
+                bike_pos.x + delta + BIKE_WIDTH_CENTER,
                 bike_pos.y - BIKE_HEIGHT_CENTER,
                 0.,
             ),
             Direction::Right => Vec3::new(
-                bike_pos.x - BIKE_DELTA - BIKE_WIDTH_CENTER,
+                bike_pos.x - delta - BIKE_WIDTH_CENTER,
                 bike_pos.y - BIKE_HEIGHT_CENTER,
                 0.,
             ),
         };
+        let trail_block_half = (delta / 2.0) - 1.;
         // after bike moves there's a gap between bike and trail that we fill.
         let entity = commands
             .spawn_bundle(MaterialMesh2dBundle {
                 mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
                 transform: Transform::default()
-                    .with_scale(Vec3::new(BIKE_DELTA, BIKE_DELTA, 1.))
+                    .with_scale(Vec3::new(delta, delta, 1.))
                     .with_translation(new_head_pos),
                 material: materials.add(ColorMaterial::from(Color::RED)),
                 ..default()
             })
             .insert(RigidBody::Static {})
             .insert(CollisionShape::Cuboid {
-                half_extends: Vec3::new(TRAIL_BLOCK_HALF, TRAIL_BLOCK_HALF, 1.),
+                half_extends: Vec3::new(trail_block_half, trail_block_half, 1.),
                 border_radius: None,
             })
             .id();
+        let cells = block_cells(bike_pos, new_head_pos);
+        for cell in &cells {
+            self.cells.insert(*cell, entity);
+        }
         self.tail.push_front(TrailBlock {
             entity,
             pos: new_head_pos,
+            cells,
         });
     }
 }
@@ -149,15 +408,32 @@ fn main() {
         })
         .insert_resource(ImageSettings::default_nearest()) // prevents blurry sprites
         .insert_resource(ClearColor(BACKGROUND_COLOR))
+        .insert_resource(WallGrid::default())
+        .insert_resource(Score::default())
+        .insert_resource(ArenaSize::default())
+        .insert_resource(ArenaSeed::default())
+        .insert_resource(MovementSettings::default())
+        .insert_resource(InputBindings::default())
+        .insert_resource(InputState::default())
+        .insert_resource(CameraSettings::default())
         .add_startup_system(setup)
+        .add_system(assign_gamepads)
+        .add_system(gather_input.after(assign_gamepads))
         .add_system_set(
             SystemSet::on_update(AppState::InGame)
                 .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
                 .with_system(check_collisions)
                 .with_system(animate_sprite)
                 .with_system(camera_system)
-                .with_system(player_movement),
+                .with_system(player_movement)
+                .with_system(ai_movement)
+                .with_system(update_score)
+                .with_system(update_score_hud),
         )
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(restart_game))
+        .add_system_set(SystemSet::on_enter(AppState::Dead).with_system(spawn_dead_overlay))
+        .add_system_set(SystemSet::on_update(AppState::Dead).with_system(handle_dead_input))
+        .add_system_set(SystemSet::on_exit(AppState::Dead).with_system(despawn_dead_overlay))
         .run();
 }
 
@@ -179,44 +455,265 @@ fn animate_sprite(
     }
 }
 
+const GAMEPAD_STICK_DEADZONE: f32 = 0.3;
+
+// Hands each connected gamepad to the first player slot that doesn't have
+// one yet, and frees the slot back up on disconnect.
+fn assign_gamepads(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut bindings: ResMut<InputBindings>,
+) {
+    for event in gamepad_events.iter() {
+        match event.event_type {
+            GamepadEventType::Connected(_) => {
+                if let Some(slot) = bindings.0.iter_mut().find(|p| p.gamepad.is_none()) {
+                    slot.gamepad = Some(event.gamepad);
+                }
+            }
+            GamepadEventType::Disconnected => {
+                for slot in &mut bindings.0 {
+                    if slot.gamepad == Some(event.gamepad) {
+                        slot.gamepad = None;
+                    }
+                }
+            }
+            GamepadEventType::ButtonChanged(_, _) | GamepadEventType::AxisChanged(_, _) => {}
+        }
+    }
+}
+
+// Resolves each player's `InputBindings` against keyboard and gamepad state
+// into the abstract `PlayerInputState` the rest of the game reads.
+fn gather_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<InputBindings>,
+    mut input_state: ResMut<InputState>,
+) {
+    input_state.0.clear();
+    for player in &bindings.0 {
+        let mut state = PlayerInputState {
+            turn_left: keyboard_input.pressed(player.keyboard[&InputAction::TurnLeft]),
+            turn_right: keyboard_input.pressed(player.keyboard[&InputAction::TurnRight]),
+            turn_up: keyboard_input.pressed(player.keyboard[&InputAction::TurnUp]),
+            turn_down: keyboard_input.pressed(player.keyboard[&InputAction::TurnDown]),
+            confirm_just_pressed: keyboard_input
+                .just_pressed(player.keyboard[&InputAction::Confirm]),
+        };
+
+        if let Some(gamepad) = player.gamepad {
+            state.turn_left |=
+                gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadLeft));
+            state.turn_right |=
+                gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadRight));
+            state.turn_up |=
+                gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadUp));
+            state.turn_down |=
+                gamepad_buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadDown));
+            state.confirm_just_pressed |=
+                gamepad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::South));
+
+            let stick_x = gamepad_axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or(0.0);
+            let stick_y = gamepad_axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or(0.0);
+            if stick_x.abs() > GAMEPAD_STICK_DEADZONE || stick_y.abs() > GAMEPAD_STICK_DEADZONE {
+                if stick_x.abs() > stick_y.abs() {
+                    state.turn_right |= stick_x > 0.0;
+                    state.turn_left |= stick_x < 0.0;
+                } else {
+                    state.turn_up |= stick_y > 0.0;
+                    state.turn_down |= stick_y < 0.0;
+                }
+            }
+        }
+
+        input_state.0.push(state);
+    }
+}
+
+fn update_score(time: Res<Time>, mut score: ResMut<Score>, trail_q: Query<&Trail, With<Player>>) {
+    score.survival_time += time.delta_seconds();
+    if let Ok(trail) = trail_q.get_single() {
+        score.trail_length = score.trail_length.max(trail.tail.len());
+    }
+}
+
+fn update_score_hud(score: Res<Score>, mut query: Query<&mut Text, With<ScoreHud>>) {
+    let mut text = match query.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    text.sections[0].value = format!(
+        "Survived: {:.1}s   Trail: {}",
+        score.survival_time, score.trail_length
+    );
+}
+
+fn dead_overlay_font(asset_server: &AssetServer) -> Handle<Font> {
+    asset_server.load("fonts/FiraSans-Bold.ttf")
+}
+
+fn spawn_dead_overlay(mut commands: Commands, asset_server: Res<AssetServer>, score: Res<Score>) {
+    let font = dead_overlay_font(&asset_server);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.7).into(),
+            ..default()
+        })
+        .insert(DeadOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                "YOU DIED",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 80.0,
+                    color: Color::RED,
+                },
+            ));
+            parent.spawn_bundle(TextBundle::from_section(
+                format!(
+                    "Survived {:.1}s - Trail length {}",
+                    score.survival_time, score.trail_length
+                ),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            ));
+            parent.spawn_bundle(TextBundle::from_section(
+                "Press Enter to retry",
+                TextStyle {
+                    font,
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+fn despawn_dead_overlay(mut commands: Commands, query: Query<Entity, With<DeadOverlay>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn handle_dead_input(input_state: Res<InputState>, mut app_state: ResMut<State<AppState>>) {
+    if input_state.0.iter().any(|state| state.confirm_just_pressed) {
+        app_state.set(AppState::InGame).unwrap();
+    }
+}
+
+// Runs whenever we (re-)enter `InGame`, i.e. after a restart: clears out the
+// previous run's bikes and trail blocks and spawns a fresh set of combatants.
+fn restart_game(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    atlas: Res<BikeAtlas>,
+    movement_settings: Res<MovementSettings>,
+    bikes_q: Query<(Entity, &Trail), With<Bike>>,
+) {
+    for (bike_entity, trail) in &bikes_q {
+        for block in &trail.tail {
+            commands.entity(block.entity).despawn();
+        }
+        commands.entity(bike_entity).despawn();
+    }
+
+    *score = Score::default();
+    spawn_combatants(&mut commands, atlas.0.clone(), &movement_settings);
+}
+
+// Current speed of a bike, inferred from the spacing of its two most recent trail blocks.
+fn current_speed(trail: &Trail) -> f32 {
+    let mut blocks = trail.tail.iter();
+    match (blocks.next(), blocks.next()) {
+        (Some(newest), Some(previous)) => (newest.pos - previous.pos).length() / TIME_STEP,
+        _ => BIKE_SPEED,
+    }
+}
+
+// Smoothly follows the player, aiming ahead of their direction and zooming with speed.
 fn camera_system(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
     mut set: ParamSet<(
-        Query<&Transform, With<Player>>,
-        Query<&mut Transform, With<Camera>>,
+        Query<(&Transform, &Bike, &Trail), With<Player>>,
+        Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
     )>,
 ) {
-    let player_pos = match set.p0().get_single() {
-        Ok(transform) => transform.translation,
+    let (player_pos, direction, speed) = match set.p0().get_single() {
+        Ok((transform, bike, trail)) => {
+            (transform.translation, bike.direction, current_speed(trail))
+        }
         Err(_) => return,
     };
-    let mut p1 = set.p1();
-    let mut camera_pos = p1.get_single_mut().unwrap();
 
-    camera_pos.translation.x = player_pos.x.round();
-    camera_pos.translation.y = player_pos.y.round();
+    let (dx, dy) = direction_vector(direction);
+    let target = player_pos + Vec3::new(dx as f32, dy as f32, 0.) * settings.look_ahead_distance;
+
+    let mut camera_q = set.p1();
+    let (mut camera_transform, mut projection) = match camera_q.get_single_mut() {
+        Ok(q) => q,
+        Err(_) => return,
+    };
+
+    let lerp_factor = (settings.stiffness * time.delta_seconds()).clamp(0.0, 1.0);
+    camera_transform.translation = camera_transform.translation.lerp(target, lerp_factor);
+
+    let speed_fraction = (speed / settings.zoom_speed_cap).clamp(0.0, 1.0);
+    projection.scale = settings.zoom_min + (settings.zoom_max - settings.zoom_min) * speed_fraction;
 }
 
 fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
+    input_state: Res<InputState>,
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
-    mut query: Query<(&mut Transform, &mut Bike, &mut Trail), With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    wall_grid: Res<WallGrid>,
+    movement_settings: Res<MovementSettings>,
+    mut app_state: ResMut<State<AppState>>,
+    mut set: ParamSet<(
+        Query<&Trail>,
+        Query<(Entity, &mut Transform, &mut Bike, &mut Trail, &mut Speed), With<Player>>,
+    )>,
 ) {
-    let (bike_transform, mut bike, trail) = match query.get_single_mut() {
+    let mut occupied = wall_grid.0.clone();
+    for trail in set.p0().iter() {
+        occupied.extend(trail.cells.keys());
+    }
+
+    let (bike_entity, bike_transform, mut bike, trail, mut speed) = match set.p1().get_single_mut()
+    {
         Ok(q) => q,
         Err(_) => return,
     };
+    // Player 0's bindings; a second local player would read `input_state.0[1]`.
+    let state = match input_state.0.first() {
+        Some(state) => state,
+        None => return,
+    };
     let bike_transform = bike_transform.into_inner();
     let prev_direction = bike.direction;
 
-    if keyboard_input.pressed(KeyCode::Left) && bike.direction != Direction::Right {
+    if state.turn_left && bike.direction != Direction::Right {
         bike.direction = Direction::Left;
-    } else if keyboard_input.pressed(KeyCode::Right) && bike.direction != Direction::Left {
+    } else if state.turn_right && bike.direction != Direction::Left {
         bike.direction = Direction::Right;
-    } else if keyboard_input.pressed(KeyCode::Down) && bike.direction != Direction::Up {
+    } else if state.turn_down && bike.direction != Direction::Up {
         bike.direction = Direction::Down;
-    } else if keyboard_input.pressed(KeyCode::Up) && bike.direction != Direction::Down {
+    } else if state.turn_up && bike.direction != Direction::Down {
         bike.direction = Direction::Up;
     }
 
@@ -226,20 +723,136 @@ fn player_movement(
         trail.into_inner(),
         &mut commands,
         prev_direction,
-        meshes,
-        materials,
+        &mut meshes,
+        &mut materials,
+        &occupied,
+        &mut app_state,
+        bike_entity,
+        true,
+        speed.as_mut(),
+        &movement_settings,
     );
 }
 
+// `Ai` marker: non-player bikes driven by `ai_movement` instead of keyboard input.
+#[derive(Component)]
+struct Ai;
+
+const AI_LOOKAHEAD_CELLS: i32 = 8;
+
+// The current direction plus the two legal perpendicular turns (bikes can't reverse).
+fn ai_candidate_directions(current: Direction) -> [Direction; 3] {
+    match current {
+        Direction::Left | Direction::Right => [current, Direction::Up, Direction::Down],
+        Direction::Up | Direction::Down => [current, Direction::Left, Direction::Right],
+    }
+}
+
+fn direction_vector(direction: Direction) -> (i32, i32) {
+    match direction {
+        Direction::Left => (-1, 0),
+        Direction::Right => (1, 0),
+        Direction::Down => (0, -1),
+        Direction::Up => (0, 1),
+    }
+}
+
+// Number of free cells ahead of `start` along `direction` before hitting an
+// occupied one, capped at `AI_LOOKAHEAD_CELLS`.
+fn free_distance(start: (i32, i32), direction: Direction, occupied: &HashSet<(i32, i32)>) -> i32 {
+    let (dx, dy) = direction_vector(direction);
+    let mut cell = start;
+    let mut distance = 0;
+    while distance < AI_LOOKAHEAD_CELLS {
+        cell = (cell.0 + dx, cell.1 + dy);
+        if occupied.contains(&cell) {
+            break;
+        }
+        distance += 1;
+    }
+    distance
+}
+
+// Mirrors `player_movement`, but picks a `Direction` from lookahead distance
+// over the trail/wall grid instead of reading keyboard input. Ties are kept on
+// the current direction so the bike doesn't jitter between equally-clear turns.
+fn ai_movement(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    wall_grid: Res<WallGrid>,
+    movement_settings: Res<MovementSettings>,
+    mut app_state: ResMut<State<AppState>>,
+    mut set: ParamSet<(
+        Query<&Trail>,
+        Query<(Entity, &mut Transform, &mut Bike, &mut Trail, &mut Speed), With<Ai>>,
+    )>,
+) {
+    let mut occupied = wall_grid.0.clone();
+    for trail in set.p0().iter() {
+        occupied.extend(trail.cells.keys());
+    }
+
+    for (bike_entity, bike_transform, mut bike, trail, mut speed) in set.p1().iter_mut() {
+        let prev_direction = bike.direction;
+        let cell = grid_cell(bike_transform.translation);
+
+        let mut best_direction = prev_direction;
+        let mut best_distance = free_distance(cell, prev_direction, &occupied);
+        for candidate in ai_candidate_directions(prev_direction) {
+            let distance = free_distance(cell, candidate, &occupied);
+            if distance > best_distance {
+                best_distance = distance;
+                best_direction = candidate;
+            }
+        }
+        bike.direction = best_direction;
+
+        let bike_transform = bike_transform.into_inner();
+        move_bike(
+            bike_transform,
+            bike,
+            trail.into_inner(),
+            &mut commands,
+            prev_direction,
+            &mut meshes,
+            &mut materials,
+            &occupied,
+            &mut app_state,
+            bike_entity,
+            false,
+            speed.as_mut(),
+            &movement_settings,
+        );
+    }
+}
+
+// Moves the bike and, independent of the physics engine's discrete overlap
+// test, sweeps the grid cells crossed between its previous and new position
+// against `occupied` (every bike's `Trail::cells` plus `WallGrid`, built by
+// the caller). This is what actually catches tunneling through thin trail
+// blocks at high speed; the `heron` sensor remains the only path for
+// `check_collisions`'s boost pickups.
+#[allow(clippy::too_many_arguments)]
 fn move_bike(
     bike_transform: &mut Transform,
     bike: Mut<Bike>,
     trail: &mut Trail,
     commands: &mut Commands,
     prev_direction: Direction,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    occupied: &HashSet<(i32, i32)>,
+    app_state: &mut ResMut<State<AppState>>,
+    bike_entity: Entity,
+    is_player: bool,
+    speed: &mut Speed,
+    movement_settings: &MovementSettings,
 ) {
+    tick_speed(speed, movement_settings);
+    let delta = movement_settings.tick_delta(speed.current);
+
+    let old_cell = grid_cell(bike_transform.translation);
     let mut rotate_point = bike_transform.translation;
     match bike.direction {
         Direction::Left => {
@@ -258,7 +871,7 @@ fn move_bike(
                 }
                 _ => (),
             }
-            bike_transform.translation.x -= BIKE_DELTA;
+            bike_transform.translation.x -= delta;
         }
         Direction::Right => {
             match prev_direction {
@@ -276,7 +889,7 @@ fn move_bike(
                 }
                 _ => (),
             }
-            bike_transform.translation.x += BIKE_DELTA;
+            bike_transform.translation.x += delta;
         }
         Direction::Down => {
             match prev_direction {
@@ -294,7 +907,7 @@ fn move_bike(
                 }
                 _ => (),
             }
-            bike_transform.translation.y -= BIKE_DELTA;
+            bike_transform.translation.y -= delta;
         }
         Direction::Up => {
             match prev_direction {
@@ -312,19 +925,57 @@ fn move_bike(
                 }
                 _ => (),
             }
-            bike_transform.translation.y += BIKE_DELTA;
+            bike_transform.translation.y += delta;
         }
     }
 
+    let new_cell = grid_cell(bike_transform.translation);
+    let swept = swept_cells(old_cell, new_cell);
+    if swept.iter().any(|cell| occupied.contains(cell)) {
+        handle_wall_collision(
+            commands,
+            is_player.then_some(bike_entity),
+            bike_entity,
+            app_state,
+        );
+        return;
+    }
+
     trail.trek(
         bike.into_inner(),
         meshes,
         materials,
         bike_transform.translation,
         commands,
+        delta,
     );
 }
 
+// Eases `speed.current` toward `speed.target`, counting an active boost down
+// to the base speed once it expires.
+fn tick_speed(speed: &mut Speed, movement_settings: &MovementSettings) {
+    let max_step = movement_settings.accel * TIME_STEP;
+    let diff = (speed.target - speed.current).clamp(-max_step, max_step);
+    speed.current += diff;
+    if speed.boost_remaining > 0.0 {
+        speed.boost_remaining = (speed.boost_remaining - TIME_STEP).max(0.0);
+        if speed.boost_remaining == 0.0 {
+            speed.target = movement_settings.base_speed;
+        }
+    }
+}
+
+// `State::set` errors if the target is already the current (or already
+// queued) state, and the player can now die via more than one path in the
+// same tick (the heron `CollisionEvent` path here and the grid-based swept
+// check in `move_bike`), so this guards the transition instead of assuming
+// only one caller reaches it per frame.
+fn enter_dead_state(app_state: &mut ResMut<State<AppState>>) {
+    if *app_state.current() != AppState::Dead {
+        app_state.overwrite_set(AppState::Dead).unwrap();
+    }
+}
+
 fn handle_wall_collision(
     commands: &mut Commands,
     player_id: Option<Entity>,
@@ -333,17 +984,78 @@ fn handle_wall_collision(
 ) {
     commands.entity(bike_id).despawn();
     if let Some(_id) = player_id {
-        app_state.set(AppState::Dead).unwrap();
+        enter_dead_state(app_state);
+    }
+}
+
+// When two bikes' sensors overlap, despawn whichever one rode into the
+// other's existing trail (it entered that cell, not the other way around).
+// If neither (or both) grid cells line up that way, it's a genuine head-on
+// crash and both go down; the remaining bike is promoted by default since
+// there's nothing else tracking "the survivor".
+fn handle_bike_collision(
+    commands: &mut Commands,
+    first: Entity,
+    second: Entity,
+    transforms_q: &Query<&Transform>,
+    trails_q: &Query<&Trail>,
+    player_id: Option<Entity>,
+    app_state: &mut ResMut<State<AppState>>,
+) {
+    let entered_trail = |entity: Entity, other: Entity| {
+        let cell = grid_cell(transforms_q.get(entity).ok()?.translation);
+        Some(trails_q.get(other).ok()?.cells.contains_key(&cell))
+    };
+    let first_entered_second = entered_trail(first, second).unwrap_or(false);
+    let second_entered_first = entered_trail(second, first).unwrap_or(false);
+
+    let (despawn_first, despawn_second) = match (first_entered_second, second_entered_first) {
+        (true, false) => (true, false),
+        (false, true) => (false, true),
+        _ => (true, true),
+    };
+
+    if despawn_first {
+        commands.entity(first).despawn();
+    }
+    if despawn_second {
+        commands.entity(second).despawn();
+    }
+
+    let player_died = (despawn_first && player_id == Some(first))
+        || (despawn_second && player_id == Some(second));
+    if player_died {
+        enter_dead_state(app_state);
     }
 }
 
-// TODO
-fn handle_bike_collision() {}
+// Applies a `Boost`'s speed bump to the colliding bike (extending rather than
+// stacking any boost already in progress) and despawns the pickup.
+fn collect_boost(
+    commands: &mut Commands,
+    speeds_q: &mut Query<&mut Speed>,
+    movement_settings: &MovementSettings,
+    boost: &Boost,
+    boost_entity: Entity,
+    bike_entity: Entity,
+) {
+    if let Ok(mut speed) = speeds_q.get_mut(bike_entity) {
+        speed.target = movement_settings.base_speed * boost.multiplier;
+        speed.boost_remaining = speed.boost_remaining.max(boost.duration);
+    }
+    commands.entity(boost_entity).despawn();
+}
 
+#[allow(clippy::too_many_arguments)]
 fn check_collisions(
     mut events: EventReader<CollisionEvent>,
     player_q: Query<Option<Entity>, With<Player>>,
     bikes_q: Query<Entity, With<Bike>>,
+    boosts_q: Query<&Boost>,
+    transforms_q: Query<&Transform>,
+    trails_q: Query<&Trail>,
+    mut speeds_q: Query<&mut Speed>,
+    movement_settings: Res<MovementSettings>,
     mut app_state: ResMut<State<AppState>>,
     mut commands: Commands,
 ) {
@@ -358,35 +1070,333 @@ fn check_collisions(
                 (first, second)
             }
         };
-        if bikes_q.contains(first.rigid_body_entity()) {
-            if bikes_q.contains(second.rigid_body_entity()) {
-                handle_bike_collision();
-            } else {
-                handle_wall_collision(
+        let first_entity = first.rigid_body_entity();
+        let second_entity = second.rigid_body_entity();
+
+        let boost_pair = if let Ok(boost) = boosts_q.get(first_entity) {
+            Some((boost, first_entity, second_entity))
+        } else {
+            boosts_q
+                .get(second_entity)
+                .ok()
+                .map(|boost| (boost, second_entity, first_entity))
+        };
+        if let Some((boost, boost_entity, bike_entity)) = boost_pair {
+            if bikes_q.contains(bike_entity) {
+                collect_boost(
                     &mut commands,
+                    &mut speeds_q,
+                    &movement_settings,
+                    boost,
+                    boost_entity,
+                    bike_entity,
+                );
+            }
+            continue;
+        }
+
+        if bikes_q.contains(first_entity) {
+            if bikes_q.contains(second_entity) {
+                handle_bike_collision(
+                    &mut commands,
+                    first_entity,
+                    second_entity,
+                    &transforms_q,
+                    &trails_q,
                     player_id,
-                    first.rigid_body_entity(),
                     &mut app_state,
                 );
                 break;
+            } else {
+                handle_wall_collision(&mut commands, player_id, first_entity, &mut app_state);
+                break;
             }
         } else {
-            handle_wall_collision(
-                &mut commands,
-                player_id,
-                second.rigid_body_entity(),
-                &mut app_state,
-            );
+            handle_wall_collision(&mut commands, player_id, second_entity, &mut app_state);
             break;
         }
     }
 }
 
+// Shared bike bundle for both `Player` and `Ai` entities; the caller attaches
+// whichever marker component decides who drives it.
+fn spawn_bike(
+    commands: &mut Commands,
+    texture_atlas_handle: Handle<TextureAtlas>,
+    position: Vec2,
+    direction: Direction,
+    base_speed: f32,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
+            transform: Transform::from_scale(Vec3::splat(1.)).with_translation(position.extend(0.)),
+            ..Default::default()
+        })
+        .insert(AnimationTimer(Timer::from_seconds(0.1, true)))
+        .insert(Bike {
+            direction,
+            atlas_handle: texture_atlas_handle,
+        })
+        .insert(RigidBody::Sensor {})
+        .insert(CollisionShape::Cuboid {
+            half_extends: Vec3::new(BIKE_WIDTH_CENTER, BIKE_HEIGHT_CENTER, 0.),
+            border_radius: None,
+        })
+        .insert(Trail::new())
+        .insert(Speed::new(base_speed))
+        .id()
+}
+
+// Spawn positions for a fresh run: the player (index 0) plus the AI opponents.
+// Also used by arena generation to keep a clear patch around each spawn.
+const COMBATANT_SPAWNS: [(f32, f32, Direction); 3] = [
+    (5., 5., Direction::Right),
+    (400., 400., Direction::Left),
+    (-400., -400., Direction::Right),
+];
+
+// Spawns the player and the AI opponents for a fresh run; used both at
+// startup and by `restart_game` after a death.
+fn spawn_combatants(
+    commands: &mut Commands,
+    texture_atlas_handle: Handle<TextureAtlas>,
+    movement_settings: &MovementSettings,
+) {
+    for (index, &(x, y, direction)) in COMBATANT_SPAWNS.iter().enumerate() {
+        let entity = spawn_bike(
+            commands,
+            texture_atlas_handle.clone(),
+            Vec2::new(x, y),
+            direction,
+            movement_settings.base_speed,
+        );
+        if index == 0 {
+            commands.entity(entity).insert(Player {});
+        } else {
+            commands.entity(entity).insert(Ai);
+        }
+    }
+}
+
+fn spawn_score_hud(commands: &mut Commands, asset_server: &AssetServer) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "Survived: 0.0s   Trail: 0",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ScoreHud);
+}
+
+const ARENA_OBSTACLE_FREQUENCY: f64 = 0.05;
+const ARENA_OBSTACLE_THRESHOLD: f64 = 0.55;
+const ARENA_SPAWN_CLEARANCE_CELLS: i32 = 6;
+
+// Sampled from a noise field decorrelated from the obstacle field (see
+// `spawn_arena_boosts`), so boosts don't cluster with the walls they'd need to
+// dodge while accelerating.
+const ARENA_BOOST_FREQUENCY: f64 = 0.07;
+const ARENA_BOOST_THRESHOLD: f64 = 0.8;
+const BOOST_SPEED_MULTIPLIER: f32 = 1.6;
+const BOOST_DURATION_SECONDS: f32 = 3.0;
+
+fn cell_center(cell: (i32, i32)) -> Vec3 {
+    Vec3::new(
+        (cell.0 as f32 + 0.5) * BIKE_DELTA,
+        (cell.1 as f32 + 0.5) * BIKE_DELTA,
+        0.,
+    )
+}
+
+// Spawns a single grid-aligned static obstacle (used for both boundary walls
+// and interior noise obstacles) and marks its cell occupied in `wall_grid` so
+// the swept-collision check in `move_bike` sees it.
+fn spawn_obstacle_block(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    wall_grid: &mut WallGrid,
+    cell: (i32, i32),
+) {
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+            transform: Transform::default()
+                .with_scale(Vec3::new(BIKE_DELTA, BIKE_DELTA, 1.))
+                .with_translation(cell_center(cell)),
+            material: materials.add(ColorMaterial::from(Color::DARK_GRAY)),
+            ..default()
+        })
+        .insert(RigidBody::Static {})
+        .insert(CollisionShape::Cuboid {
+            half_extends: Vec3::new(TRAIL_BLOCK_HALF, TRAIL_BLOCK_HALF, 1.),
+            border_radius: None,
+        });
+    wall_grid.0.insert(cell);
+}
+
+fn spawn_arena_walls(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    wall_grid: &mut WallGrid,
+    arena_size: &ArenaSize,
+) {
+    let half_width = arena_size.half_width_cells;
+    let half_height = arena_size.half_height_cells;
+    for x in -half_width..=half_width {
+        spawn_obstacle_block(commands, meshes, materials, wall_grid, (x, half_height));
+        spawn_obstacle_block(commands, meshes, materials, wall_grid, (x, -half_height));
+    }
+    for y in -half_height..=half_height {
+        spawn_obstacle_block(commands, meshes, materials, wall_grid, (half_width, y));
+        spawn_obstacle_block(commands, meshes, materials, wall_grid, (-half_width, y));
+    }
+}
+
+// Keeps a clear patch around every combatant's spawn point so no one starts
+// boxed in by an obstacle.
+fn is_spawn_clearance(cell: (i32, i32)) -> bool {
+    COMBATANT_SPAWNS.iter().any(|&(x, y, _)| {
+        let spawn_cell = grid_cell(Vec3::new(x, y, 0.));
+        (cell.0 - spawn_cell.0).abs() <= ARENA_SPAWN_CLEARANCE_CELLS
+            && (cell.1 - spawn_cell.1).abs() <= ARENA_SPAWN_CLEARANCE_CELLS
+    })
+}
+
+fn spawn_arena_obstacles(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    wall_grid: &mut WallGrid,
+    arena_size: &ArenaSize,
+    arena_seed: &ArenaSeed,
+) {
+    let perlin = Perlin::new(arena_seed.0);
+    let half_width = arena_size.half_width_cells - 1;
+    let half_height = arena_size.half_height_cells - 1;
+    for x in -half_width..half_width {
+        for y in -half_height..half_height {
+            let cell = (x, y);
+            if is_spawn_clearance(cell) {
+                continue;
+            }
+            let noise_value = perlin.get([
+                x as f64 * ARENA_OBSTACLE_FREQUENCY,
+                y as f64 * ARENA_OBSTACLE_FREQUENCY,
+            ]);
+            if noise_value > ARENA_OBSTACLE_THRESHOLD {
+                spawn_obstacle_block(commands, meshes, materials, wall_grid, cell);
+            }
+        }
+    }
+}
+
+// Spawns a speed pickup at a cell's center; unlike `spawn_obstacle_block` it's
+// a `Sensor` (bikes pass through it) and isn't added to `wall_grid`, so it
+// never blocks movement, only grants a boost on contact (see `collect_boost`).
+fn spawn_boost(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    cell: (i32, i32),
+) {
+    let half_extent = TRAIL_BLOCK_HALF * 0.5;
+    commands
+        .spawn_bundle(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+            transform: Transform::default()
+                .with_scale(Vec3::new(half_extent * 2., half_extent * 2., 1.))
+                .with_translation(cell_center(cell)),
+            material: materials.add(ColorMaterial::from(Color::YELLOW)),
+            ..default()
+        })
+        .insert(RigidBody::Sensor {})
+        .insert(CollisionShape::Cuboid {
+            half_extends: Vec3::new(half_extent, half_extent, 1.),
+            border_radius: None,
+        })
+        .insert(Boost {
+            multiplier: BOOST_SPEED_MULTIPLIER,
+            duration: BOOST_DURATION_SECONDS,
+        });
+}
+
+// Scatters boost pickups over the same grid as the obstacle field, using a
+// noise field seeded off `arena_seed` so a given seed's boost layout is also
+// reproducible, and skipping cells already claimed by a wall/obstacle or spawn
+// clearance.
+fn spawn_arena_boosts(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    wall_grid: &WallGrid,
+    arena_size: &ArenaSize,
+    arena_seed: &ArenaSeed,
+) {
+    let perlin = Perlin::new(arena_seed.0.wrapping_add(1));
+    let half_width = arena_size.half_width_cells - 1;
+    let half_height = arena_size.half_height_cells - 1;
+    for x in -half_width..half_width {
+        for y in -half_height..half_height {
+            let cell = (x, y);
+            if is_spawn_clearance(cell) || wall_grid.0.contains(&cell) {
+                continue;
+            }
+            let noise_value = perlin.get([
+                x as f64 * ARENA_BOOST_FREQUENCY,
+                y as f64 * ARENA_BOOST_FREQUENCY,
+            ]);
+            if noise_value > ARENA_BOOST_THRESHOLD {
+                spawn_boost(commands, meshes, materials, cell);
+            }
+        }
+    }
+}
+
+fn spawn_arena(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    wall_grid: &mut WallGrid,
+    arena_size: &ArenaSize,
+    arena_seed: &ArenaSeed,
+) {
+    spawn_arena_walls(commands, meshes, materials, wall_grid, arena_size);
+    spawn_arena_obstacles(
+        commands, meshes, materials, wall_grid, arena_size, arena_seed,
+    );
+    spawn_arena_boosts(
+        commands, meshes, materials, wall_grid, arena_size, arena_seed,
+    );
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut windows: ResMut<window::Windows>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut wall_grid: ResMut<WallGrid>,
+    arena_size: Res<ArenaSize>,
+    arena_seed: Res<ArenaSeed>,
+    movement_settings: Res<MovementSettings>,
     // audio: Res<Audio>,
 ) {
     let window = windows.get_primary_mut().unwrap();
@@ -400,25 +1410,84 @@ fn setup(
     let camera_bundle = Camera2dBundle::new_with_far(3.);
     commands.spawn_bundle(camera_bundle).insert(MainCamera);
 
-    commands
-        .spawn_bundle(SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle.clone(),
-            transform: Transform::from_scale(Vec3::splat(1.))
-                .with_translation(Vec2::splat(5.).extend(0.)),
-            ..Default::default()
-        })
-        .insert(AnimationTimer(Timer::from_seconds(0.1, true)))
-        .insert(Bike {
-            direction: Direction::Right,
-            atlas_handle: texture_atlas_handle,
-        })
-        .insert(RigidBody::Sensor {})
-        .insert(CollisionShape::Cuboid {
-            half_extends: Vec3::new(BIKE_WIDTH_CENTER, BIKE_HEIGHT_CENTER, 0.),
-            border_radius: None,
-        })
-        .insert(Player {})
-        .insert(Trail::new());
+    commands.insert_resource(BikeAtlas(texture_atlas_handle.clone()));
+    spawn_combatants(&mut commands, texture_atlas_handle, &movement_settings);
+    spawn_score_hud(&mut commands, &asset_server);
+    spawn_arena(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut wall_grid,
+        &arena_size,
+        &arena_seed,
+    );
 
     // audio.play(music);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swept_cells_covers_every_intermediate_cell_of_a_multi_cell_jump() {
+        assert_eq!(swept_cells((0, 0), (3, 0)), vec![(1, 0), (2, 0), (3, 0)]);
+        assert_eq!(swept_cells((0, 0), (0, -2)), vec![(0, -1), (0, -2)]);
+        assert_eq!(swept_cells((2, 2), (2, 2)), Vec::new());
+    }
+
+    #[test]
+    fn grid_cell_floors_onto_the_bike_delta_grid() {
+        assert_eq!(grid_cell(Vec3::new(0., 0., 0.)), (0, 0));
+        assert_eq!(grid_cell(Vec3::new(BIKE_DELTA - 0.1, 0., 0.)), (0, 0));
+        assert_eq!(grid_cell(Vec3::new(BIKE_DELTA, 0., 0.)), (1, 0));
+        assert_eq!(grid_cell(Vec3::new(-0.1, 0., 0.)), (-1, 0));
+    }
+
+    #[test]
+    fn ai_candidate_directions_never_includes_the_reverse() {
+        for candidate in ai_candidate_directions(Direction::Left) {
+            assert_ne!(candidate, Direction::Right);
+        }
+        for candidate in ai_candidate_directions(Direction::Up) {
+            assert_ne!(candidate, Direction::Down);
+        }
+    }
+
+    #[test]
+    fn free_distance_stops_at_the_first_occupied_cell() {
+        let mut occupied = HashSet::new();
+        occupied.insert((2, 0));
+        assert_eq!(free_distance((0, 0), Direction::Right, &occupied), 1);
+        assert_eq!(
+            free_distance((0, 0), Direction::Up, &occupied),
+            AI_LOOKAHEAD_CELLS
+        );
+    }
+
+    #[test]
+    fn block_cells_covers_a_boosted_blocks_full_footprint() {
+        // A boosted step spans more than one BIKE_DELTA cell; block_cells
+        // must index all of them, not just the block's center.
+        let start = Vec3::new(0., 0., 0.);
+        let end = Vec3::new(0., BIKE_DELTA * 1.6, 0.);
+        assert_eq!(block_cells(start, end), vec![(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn tick_speed_ramps_toward_target_and_expires_boost() {
+        let settings = MovementSettings {
+            base_speed: 400.0,
+            accel: 400.0 * 4.0,
+        };
+        let mut speed = Speed::new(settings.base_speed);
+        speed.target = settings.base_speed * BOOST_SPEED_MULTIPLIER;
+        speed.boost_remaining = TIME_STEP;
+
+        tick_speed(&mut speed, &settings);
+
+        assert_eq!(speed.boost_remaining, 0.0);
+        assert_eq!(speed.target, settings.base_speed);
+        assert!(speed.current > settings.base_speed);
+    }
+}